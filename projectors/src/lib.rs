@@ -1,8 +1,140 @@
 use ndarray::prelude::*;
+use ndarray::{ArrayView2, ArrayView3, ArrayView4};
 use numpy::ToPyArray;
-use numpy::{PyArray3, PyArray4};
+use numpy::{PyArray2, PyArray3, PyArray4, PyReadonlyArray2, PyReadonlyArray3, PyReadonlyArray4};
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use pyo3::wrap_pyfunction;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use std::collections::HashMap;
+use std::collections::HashSet;
+
+// Per-task scatter state for the parallel path; the trailing index arrays
+// record which point last won each voxel, for tie-breaking on merge.
+type Accumulator = (
+    Array4<u64>,
+    Array3<f64>,
+    Array3<f64>,
+    Array3<f64>,
+    Array3<i64>,
+    Array3<i64>,
+);
+
+fn empty_accumulator(
+    images: usize,
+    rows: usize,
+    columns: usize,
+    channels: usize,
+    num_directions: usize,
+    max_bound_f64: f64,
+) -> Accumulator {
+    let img = Array::from_elem((images, rows, columns, channels), 255u64);
+    let ocp_map = Array::zeros((images, rows, columns));
+    let min_depth = Array::zeros((num_directions, rows, columns));
+    let max_depth = Array::from_elem((num_directions, rows, columns), max_bound_f64);
+    let min_idx = Array::from_elem((num_directions, rows, columns), -1i64);
+    let max_idx = Array::from_elem((num_directions, rows, columns), -1i64);
+    return (img, ocp_map, min_depth, max_depth, min_idx, max_idx);
+}
+
+// Bin a single rotated point: nearest wins max_depth, farthest wins min_depth.
+fn accumulate_point(
+    img: &mut Array4<u64>,
+    ocp_map: &mut Array3<f64>,
+    min_depth: &mut Array3<f64>,
+    max_depth: &mut Array3<f64>,
+    min_idx: &mut Array3<i64>,
+    max_idx: &mut Array3<i64>,
+    rotated_points: &Vec<Vec<[f64; 3]>>,
+    offsets: &Vec<[f64; 3]>,
+    colors_f: &Array2<u64>,
+    num_directions: usize,
+    max_bound_f64: f64,
+    i: usize,
+) {
+    for d in 0..num_directions {
+        let r = &rotated_points[d][i];
+        let offset = &offsets[d];
+        let shifted = [r[0] - offset[0], r[1] - offset[1], r[2] - offset[2]];
+        if shifted[0] >= max_bound_f64
+            || shifted[1] >= max_bound_f64
+            || shifted[2] >= max_bound_f64
+            || shifted[0] < 0.0
+            || shifted[1] < 0.0
+            || shifted[2] < 0.0
+        {
+            continue;
+        }
+        let k1 = shifted[0] as usize;
+        let k2 = shifted[1] as usize;
+        let depth = shifted[2];
+        if depth <= max_depth[[d, k1, k2]] {
+            img.slice_mut(s![2 * d, k1, k2, ..])
+                .assign(&colors_f.slice(s![i, ..]));
+            ocp_map[[2 * d, k1, k2]] = 1.0;
+            max_depth[[d, k1, k2]] = depth;
+            max_idx[[d, k1, k2]] = i as i64;
+        }
+        if depth >= min_depth[[d, k1, k2]] {
+            img.slice_mut(s![2 * d + 1, k1, k2, ..])
+                .assign(&colors_f.slice(s![i, ..]));
+            ocp_map[[2 * d + 1, k1, k2]] = 1.0;
+            min_depth[[d, k1, k2]] = depth;
+            min_idx[[d, k1, k2]] = i as i64;
+        }
+    }
+}
+
+// Merge two accumulators; exact-depth ties go to the higher point index, to
+// match the sequential loop's last-write-wins rule regardless of sharding.
+fn merge_accumulators(
+    mut a: Accumulator,
+    b: Accumulator,
+    num_directions: usize,
+    rows: usize,
+    columns: usize,
+) -> Accumulator {
+    {
+        let (a_img, a_ocp, a_min, a_max, a_min_idx, a_max_idx) =
+            (&mut a.0, &mut a.1, &mut a.2, &mut a.3, &mut a.4, &mut a.5);
+        let (b_img, b_ocp, b_min, b_max, b_min_idx, b_max_idx) =
+            (&b.0, &b.1, &b.2, &b.3, &b.4, &b.5);
+        for d in 0..num_directions {
+            for i in 0..rows {
+                for j in 0..columns {
+                    if b_ocp[[2 * d, i, j]] == 1.0
+                        && (a_ocp[[2 * d, i, j]] != 1.0
+                            || b_max[[d, i, j]] < a_max[[d, i, j]]
+                            || (b_max[[d, i, j]] == a_max[[d, i, j]]
+                                && b_max_idx[[d, i, j]] > a_max_idx[[d, i, j]]))
+                    {
+                        a_max[[d, i, j]] = b_max[[d, i, j]];
+                        a_max_idx[[d, i, j]] = b_max_idx[[d, i, j]];
+                        a_ocp[[2 * d, i, j]] = 1.0;
+                        a_img
+                            .slice_mut(s![2 * d, i, j, ..])
+                            .assign(&b_img.slice(s![2 * d, i, j, ..]));
+                    }
+                    if b_ocp[[2 * d + 1, i, j]] == 1.0
+                        && (a_ocp[[2 * d + 1, i, j]] != 1.0
+                            || b_min[[d, i, j]] > a_min[[d, i, j]]
+                            || (b_min[[d, i, j]] == a_min[[d, i, j]]
+                                && b_min_idx[[d, i, j]] > a_min_idx[[d, i, j]]))
+                    {
+                        a_min[[d, i, j]] = b_min[[d, i, j]];
+                        a_min_idx[[d, i, j]] = b_min_idx[[d, i, j]];
+                        a_ocp[[2 * d + 1, i, j]] = 1.0;
+                        a_img
+                            .slice_mut(s![2 * d + 1, i, j, ..])
+                            .assign(&b_img.slice(s![2 * d + 1, i, j, ..]));
+                    }
+                }
+            }
+        }
+    }
+    return a;
+}
 
 fn vec_to_2d_with_floor(vec: &Vec<Vec<f64>>) -> Array2<u64> {
     let nrows = vec.len();
@@ -15,6 +147,294 @@ fn vec_to_2d_with_floor(vec: &Vec<Vec<f64>>) -> Array2<u64> {
     return array;
 }
 
+// Neighbor offsets: 0 = 3x3 cross (4-connected), 1 = 3x3 square (8-connected).
+fn structuring_offsets(structure: u64) -> Vec<(i64, i64)> {
+    if structure == 1 {
+        vec![
+            (-1, -1),
+            (-1, 0),
+            (-1, 1),
+            (0, -1),
+            (0, 1),
+            (1, -1),
+            (1, 0),
+            (1, 1),
+        ]
+    } else {
+        vec![(-1, 0), (1, 0), (0, -1), (0, 1)]
+    }
+}
+
+// Output is 1 only if every covered neighbor is 1; off-grid counts as 0.
+fn binary_erode(slice: &Array2<f64>, structure: u64) -> Array2<f64> {
+    let (rows, cols) = slice.dim();
+    let offsets = structuring_offsets(structure);
+    let mut out = Array2::zeros((rows, cols));
+    for i in 0..rows {
+        for j in 0..cols {
+            if slice[[i, j]] != 1.0 {
+                continue;
+            }
+            let mut keep = true;
+            for (di, dj) in &offsets {
+                let ni = i as i64 + di;
+                let nj = j as i64 + dj;
+                let occupied = ni >= 0
+                    && nj >= 0
+                    && (ni as usize) < rows
+                    && (nj as usize) < cols
+                    && slice[[ni as usize, nj as usize]] == 1.0;
+                if !occupied {
+                    keep = false;
+                    break;
+                }
+            }
+            out[[i, j]] = if keep { 1.0 } else { 0.0 };
+        }
+    }
+    return out;
+}
+
+// Output is 1 if any covered neighbor is 1; off-grid counts as 0.
+fn binary_dilate(slice: &Array2<f64>, structure: u64) -> Array2<f64> {
+    let (rows, cols) = slice.dim();
+    let offsets = structuring_offsets(structure);
+    let mut out = Array2::zeros((rows, cols));
+    for i in 0..rows {
+        for j in 0..cols {
+            if slice[[i, j]] == 1.0 {
+                out[[i, j]] = 1.0;
+                continue;
+            }
+            let mut covered = false;
+            for (di, dj) in &offsets {
+                let ni = i as i64 + di;
+                let nj = j as i64 + dj;
+                if ni >= 0
+                    && nj >= 0
+                    && (ni as usize) < rows
+                    && (nj as usize) < cols
+                    && slice[[ni as usize, nj as usize]] == 1.0
+                {
+                    covered = true;
+                    break;
+                }
+            }
+            out[[i, j]] = if covered { 1.0 } else { 0.0 };
+        }
+    }
+    return out;
+}
+
+// Color of the nearest occupied pixel, searching outward ring by ring.
+fn nearest_occupied_color(
+    img: &Array4<u64>,
+    ocp_map: &Array2<f64>,
+    k: usize,
+    i: usize,
+    j: usize,
+    max_radius: i64,
+) -> Option<Vec<u64>> {
+    let (rows, cols) = ocp_map.dim();
+    for radius in 1..=max_radius {
+        for di in -radius..=radius {
+            for dj in -radius..=radius {
+                if di.abs() != radius && dj.abs() != radius {
+                    continue;
+                }
+                let ni = i as i64 + di;
+                let nj = j as i64 + dj;
+                if ni < 0 || nj < 0 || (ni as usize) >= rows || (nj as usize) >= cols {
+                    continue;
+                }
+                let (ni, nj) = (ni as usize, nj as usize);
+                if ocp_map[[ni, nj]] == 1.0 {
+                    return Some(img.slice(s![k, ni, nj, ..]).to_vec());
+                }
+            }
+        }
+    }
+    return None;
+}
+
+// Binary opening (erode then dilate) or closing (dilate then erode),
+// `iterations` times (an `iterations` of 0 runs one pass), matching
+// `scipy.ndimage`'s functions of the same name for `iterations` >= 1.
+fn apply_morphology(slice: &Array2<f64>, morphology: u64, structure: u64, iterations: u64) -> Array2<f64> {
+    let reps = iterations.max(1);
+    let mut result = slice.clone();
+    if morphology == 1 {
+        for _ in 0..reps {
+            result = binary_erode(&result, structure);
+        }
+        for _ in 0..reps {
+            result = binary_dilate(&result, structure);
+        }
+    } else if morphology == 2 {
+        for _ in 0..reps {
+            result = binary_dilate(&result, structure);
+        }
+        for _ in 0..reps {
+            result = binary_erode(&result, structure);
+        }
+    }
+    return result;
+}
+
+// DC prediction: mean of occupied pixels above and to the left, within `window`.
+fn predict_dc(
+    img: &Array4<u64>,
+    ocp_map: &Array3<f64>,
+    k: usize,
+    i: usize,
+    j: usize,
+    channels: usize,
+    window: usize,
+) -> Vec<u64> {
+    let (_, rows, columns) = ocp_map.dim();
+    let mut sums = vec![0u64; channels];
+    let mut count: u64 = 0;
+    if i > 0 {
+        let row = i - 1;
+        let lo = j.saturating_sub(window);
+        let hi = (j + window).min(columns - 1);
+        for jj in lo..=hi {
+            if ocp_map[[k, row, jj]] != 0.0 {
+                for c in 0..channels {
+                    sums[c] += img[[k, row, jj, c]];
+                }
+                count += 1;
+            }
+        }
+    }
+    if j > 0 {
+        let col = j - 1;
+        let lo = i.saturating_sub(window);
+        let hi = (i + window).min(rows - 1);
+        for ii in lo..=hi {
+            if ocp_map[[k, ii, col]] != 0.0 {
+                for c in 0..channels {
+                    sums[c] += img[[k, ii, col, c]];
+                }
+                count += 1;
+            }
+        }
+    }
+    if count == 0 {
+        return vec![255; channels];
+    }
+    return sums.iter().map(|&s| s / count).collect();
+}
+
+// Horizontal prediction: copy the nearest occupied pixel to the left.
+fn predict_horizontal(
+    img: &Array4<u64>,
+    ocp_map: &Array3<f64>,
+    k: usize,
+    i: usize,
+    j: usize,
+    channels: usize,
+) -> Vec<u64> {
+    for jj in (0..j).rev() {
+        if ocp_map[[k, i, jj]] != 0.0 {
+            return img.slice(s![k, i, jj, ..]).to_vec();
+        }
+    }
+    return vec![255; channels];
+}
+
+// Vertical prediction: copy the nearest occupied pixel above.
+fn predict_vertical(
+    img: &Array4<u64>,
+    ocp_map: &Array3<f64>,
+    k: usize,
+    i: usize,
+    j: usize,
+    channels: usize,
+) -> Vec<u64> {
+    for ii in (0..i).rev() {
+        if ocp_map[[k, ii, j]] != 0.0 {
+            return img.slice(s![k, ii, j, ..]).to_vec();
+        }
+    }
+    return vec![255; channels];
+}
+
+// Rotate a 3D point by a row-major 3x3 matrix: out = matrix * point.
+fn rotate_point(point: &[f64], matrix: &[[f64; 3]; 3]) -> [f64; 3] {
+    let mut out = [0.0; 3];
+    for r in 0..3 {
+        out[r] = matrix[r][0] * point[0] + matrix[r][1] * point[1] + matrix[r][2] * point[2];
+    }
+    return out;
+}
+
+// The 6 axis-aligned faces, as rotation matrices matching the original
+// hard-coded `plane` array (row 2 is the depth axis, rows 0/1 the in-plane axes).
+fn default_view_matrices() -> Vec<[[f64; 3]; 3]> {
+    vec![
+        [[0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [1.0, 0.0, 0.0]],
+        [[1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]],
+        [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+    ]
+}
+
+// Undo `rotate_point`: out = matrix^T * point (valid since these are rotations).
+fn inverse_rotate_point(point: &[f64; 3], matrix: &[[f64; 3]; 3]) -> [f64; 3] {
+    let mut out = [0.0; 3];
+    for c in 0..3 {
+        out[c] =
+            matrix[0][c] * point[0] + matrix[1][c] * point[1] + matrix[2][c] * point[2];
+    }
+    return out;
+}
+
+fn matmul3(a: &[[f64; 3]; 3], b: &[[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut out = [[0.0; 3]; 3];
+    for r in 0..3 {
+        for c in 0..3 {
+            out[r][c] = a[r][0] * b[0][c] + a[r][1] * b[1][c] + a[r][2] * b[2][c];
+        }
+    }
+    return out;
+}
+
+// Rotation matrix from a yaw/pitch/roll triple (radians): Rz(yaw) * Ry(pitch) * Rx(roll).
+fn euler_to_matrix(yaw: f64, pitch: f64, roll: f64) -> [[f64; 3]; 3] {
+    let (sy, cy) = yaw.sin_cos();
+    let (sp, cp) = pitch.sin_cos();
+    let (sr, cr) = roll.sin_cos();
+    let rz = [[cy, -sy, 0.0], [sy, cy, 0.0], [0.0, 0.0, 1.0]];
+    let ry = [[cp, 0.0, sp], [0.0, 1.0, 0.0], [-sp, 0.0, cp]];
+    let rx = [[1.0, 0.0, 0.0], [0.0, cr, -sr], [0.0, sr, cr]];
+    return matmul3(&matmul3(&rz, &ry), &rx);
+}
+
+// Each entry is either a full 3x3 matrix or a single-row [yaw, pitch, roll] triple.
+fn view_directions_to_matrices(view_directions: &Vec<Vec<Vec<f64>>>) -> Result<Vec<[[f64; 3]; 3]>, String> {
+    view_directions
+        .iter()
+        .map(|m| {
+            if m.len() == 1 && m[0].len() == 3 {
+                return Ok(euler_to_matrix(m[0][0], m[0][1], m[0][2]));
+            }
+            if m.len() != 3 || m.iter().any(|row| row.len() != 3) {
+                return Err(
+                    "each view_directions entry must be a 3x3 rotation matrix or a single [yaw, pitch, roll] row"
+                        .to_string(),
+                );
+            }
+            let mut out = [[0.0; 3]; 3];
+            for r in 0..3 {
+                for c in 0..3 {
+                    out[r][c] = m[r][c];
+                }
+            }
+            Ok(out)
+        })
+        .collect()
+}
+
 #[pyfunction]
 fn orthographic_projection(
     py: Python,
@@ -22,96 +442,737 @@ fn orthographic_projection(
     colors: Vec<Vec<f64>>,
     precision: u64,
     filtering: u64,
-) -> (&PyArray4<u64>, &PyArray3<f64>) {
+    morphology: u64,
+    structure: u64,
+    iterations: u64,
+    intra_prediction: u64,
+    intra_window: u64,
+    view_directions: Vec<Vec<Vec<f64>>>,
+    threads: u64,
+) -> PyResult<(
+    &PyArray4<u64>,
+    &PyArray3<f64>,
+    &PyArray3<f64>,
+    &PyArray3<f64>,
+    &PyArray2<f64>,
+)> {
+    let (img, ocp_map, min_depth, max_depth, offsets_arr) = orthographic_projection_core(
+        points,
+        colors,
+        precision,
+        filtering,
+        morphology,
+        structure,
+        iterations,
+        intra_prediction,
+        intra_window,
+        view_directions,
+        threads,
+    )
+    .map_err(PyValueError::new_err)?;
+    return Ok((
+        img.to_pyarray(py),
+        ocp_map.to_pyarray(py),
+        min_depth.to_pyarray(py),
+        max_depth.to_pyarray(py),
+        offsets_arr.to_pyarray(py),
+    ));
+}
+
+// Plain-Rust core behind `orthographic_projection`, free of PyO3 types so it
+// can be exercised directly from tests.
+#[allow(clippy::too_many_arguments)]
+fn orthographic_projection_core(
+    points: Vec<Vec<f64>>,
+    colors: Vec<Vec<f64>>,
+    precision: u64,
+    filtering: u64,
+    morphology: u64,
+    structure: u64,
+    iterations: u64,
+    intra_prediction: u64,
+    intra_window: u64,
+    view_directions: Vec<Vec<Vec<f64>>>,
+    threads: u64,
+) -> Result<(Array4<u64>, Array3<f64>, Array3<f64>, Array3<f64>, Array2<f64>), String> {
     let max_bound: u64 = 1 << precision;
     let max_bound_f64: f64 = max_bound as f64;
     let max_bound_u = max_bound as usize;
     let rows: usize = max_bound as usize;
     let columns: usize = rows;
     let channels: usize = 3;
-    let images: usize = 6;
+    let using_default_directions = view_directions.is_empty();
+    let matrices = if using_default_directions {
+        default_view_matrices()
+    } else {
+        view_directions_to_matrices(&view_directions)?
+    };
+    let num_directions = matrices.len();
+    let images: usize = 2 * num_directions;
     let initial_colors: u64 = 255;
     let mut img = Array::from_elem((images, rows, columns, channels), initial_colors);
     let mut ocp_map = Array::zeros((images, rows, columns));
-    let mut min_depth = Array::zeros((channels, rows, columns));
-    let mut max_depth = Array::from_elem((channels, rows, columns), max_bound_f64);
-    let plane: [(usize, usize); 3] = [(1, 2), (0, 2), (0, 1)];
+    let mut min_depth = Array::zeros((num_directions, rows, columns));
+    let mut max_depth = Array::from_elem((num_directions, rows, columns), max_bound_f64);
+    let mut min_idx = Array::from_elem((num_directions, rows, columns), -1i64);
+    let mut max_idx = Array::from_elem((num_directions, rows, columns), -1i64);
     let total_rows = points.len() as usize;
-    let points_f = vec_to_2d_with_floor(&points);
     let colors_f = vec_to_2d_with_floor(&colors);
-    for i in 0..total_rows {
-        if points[i][0] >= max_bound_f64
-            || points[i][1] >= max_bound_f64
-            || points[i][2] >= max_bound_f64
-        {
-            continue;
-        }
-        for j in 0usize..3usize {
-            let k1 = points_f[[i, plane[j].0]] as usize;
-            let k2 = points_f[[i, plane[j].1]] as usize;
-            if points[i][j] <= max_depth[[j, k1, k2]] {
-                img.slice_mut(s![2 * j, k1, k2, ..])
-                    .assign(&colors_f.slice(s![i, ..]));
-                ocp_map[[2 * j, k1, k2]] = 1.0;
-                max_depth[[j, k1, k2]] = points[i][j];
+    // Rotate every point into each view direction up front to re-derive offsets.
+    let mut rotated_points: Vec<Vec<[f64; 3]>> = Vec::with_capacity(num_directions);
+    let mut offsets: Vec<[f64; 3]> = Vec::with_capacity(num_directions);
+    for matrix in &matrices {
+        let mut rotated: Vec<[f64; 3]> = Vec::with_capacity(total_rows);
+        let mut mins = [f64::INFINITY; 3];
+        for i in 0..total_rows {
+            let r = rotate_point(&points[i], matrix);
+            for c in 0..3 {
+                if r[c] < mins[c] {
+                    mins[c] = r[c];
+                }
             }
-            if points[i][j] >= min_depth[[j, k1, k2]] {
-                img.slice_mut(s![2 * j + 1, k1, k2, ..])
-                    .assign(&colors_f.slice(s![i, ..]));
-                ocp_map[[2 * j + 1, k1, k2]] = 1.0;
-                min_depth[[j, k1, k2]] = points[i][j];
+            rotated.push(r);
+        }
+        let offset = if using_default_directions {
+            [0.0, 0.0, 0.0]
+        } else {
+            [mins[0].floor(), mins[1].floor(), mins[2].floor()]
+        };
+        // Oblique rotations can grow the bounding box past max_bound; warn
+        // instead of dropping those points silently.
+        if !using_default_directions {
+            let dropped = rotated
+                .iter()
+                .filter(|r| {
+                    let shifted = [r[0] - offset[0], r[1] - offset[1], r[2] - offset[2]];
+                    shifted[0] < 0.0
+                        || shifted[1] < 0.0
+                        || shifted[2] < 0.0
+                        || shifted[0] >= max_bound_f64
+                        || shifted[1] >= max_bound_f64
+                        || shifted[2] >= max_bound_f64
+                })
+                .count();
+            if dropped > 0 {
+                println!(
+                    "{} points dropped from view direction {} (rotated extent exceeds max_bound; raise precision for oblique views)",
+                    dropped,
+                    rotated_points.len()
+                );
             }
         }
+        rotated_points.push(rotated);
+        offsets.push(offset);
+    }
+    // threads == 0: sequential scatter loop. threads > 0: split across a
+    // rayon pool and merge the per-task accumulators back together.
+    if threads == 0 {
+        for i in 0..total_rows {
+            accumulate_point(
+                &mut img,
+                &mut ocp_map,
+                &mut min_depth,
+                &mut max_depth,
+                &mut min_idx,
+                &mut max_idx,
+                &rotated_points,
+                &offsets,
+                &colors_f,
+                num_directions,
+                max_bound_f64,
+                i,
+            );
+        }
+    } else {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads as usize)
+            .build()
+            .unwrap();
+        // Bound split count to roughly one chunk per thread, since each
+        // chunk allocates a full-size accumulator.
+        let min_len = (total_rows / (threads as usize).max(1)).max(1);
+        let (par_img, par_ocp, par_min, par_max, _par_min_idx, _par_max_idx) = pool.install(|| {
+            (0..total_rows)
+                .into_par_iter()
+                .with_min_len(min_len)
+                .fold(
+                    || empty_accumulator(images, rows, columns, channels, num_directions, max_bound_f64),
+                    |mut acc, i| {
+                        accumulate_point(
+                            &mut acc.0,
+                            &mut acc.1,
+                            &mut acc.2,
+                            &mut acc.3,
+                            &mut acc.4,
+                            &mut acc.5,
+                            &rotated_points,
+                            &offsets,
+                            &colors_f,
+                            num_directions,
+                            max_bound_f64,
+                            i,
+                        );
+                        acc
+                    },
+                )
+                .reduce(
+                    || empty_accumulator(images, rows, columns, channels, num_directions, max_bound_f64),
+                    |a, b| merge_accumulators(a, b, num_directions, rows, columns),
+                )
+        });
+        img = par_img;
+        ocp_map = par_ocp;
+        min_depth = par_min;
+        max_depth = par_max;
     }
     let w = filtering as u64;
-    if w == 0 {
-        return (img.to_pyarray(py), ocp_map.to_pyarray(py));
-    }
-    let mut freqs: [u64; 6] = [0, 0, 0, 0, 0, 0];
-    let w_u = w as usize;
-    let mut bias: f64;
-    for i in w_u..(max_bound_u - w_u) {
-        for j in w_u..(max_bound_u - w_u) {
-            bias = 1.0;
-            for k in 0usize..6usize {
-                let depth_idx: usize = (k / 2) as usize;
-                let curr_depth = if bias == 1.0 {
-                    &mut max_depth
-                } else {
-                    &mut min_depth
-                };
-                let curr_depth_slice = &curr_depth.slice(s![
-                    depth_idx,
-                    (i - w_u)..(i + w_u + 1),
-                    (j - w_u)..(j + w_u + 1)
-                ]);
-                let ocp_map_slice = &ocp_map.slice(s![
-                    k,
-                    (i - w_u)..(i + w_u + 1),
-                    (j - w_u)..(j + w_u + 1)
-                ]);
-                let curr_depth_filtered = curr_depth_slice * ocp_map_slice;
-                let weighted_local_average =
-                    (curr_depth_filtered.sum() / (ocp_map_slice.sum())) + bias * 20.0;
-                if ocp_map[[k, i, j]] == 1.0
-                    && curr_depth[[depth_idx, i, j]] * bias > weighted_local_average * bias
+    if w > 0 {
+        let mut freqs: Vec<u64> = vec![0; images];
+        let w_u = w as usize;
+        // Both branches decide every removal against the same untouched
+        // snapshot of ocp_map/max_depth/min_depth and apply the writes only
+        // after the scan, so threads == 0 and threads > 0 agree on exactly
+        // the same set of removed pixels: splitting the row range across
+        // threads can't change which pixel sees which neighbor's value.
+        let row_range = w_u..(max_bound_u - w_u);
+        let decide_row = |i: usize| -> Vec<(usize, usize, usize)> {
+            let mut row_updates = Vec::new();
+            for j in w_u..(max_bound_u - w_u) {
+                let mut bias: f64 = 1.0;
+                for k in 0usize..images {
+                    let depth_idx: usize = k / 2;
+                    let curr_depth = if bias == 1.0 { &max_depth } else { &min_depth };
+                    let curr_depth_slice = curr_depth.slice(s![
+                        depth_idx,
+                        (i - w_u)..(i + w_u + 1),
+                        (j - w_u)..(j + w_u + 1)
+                    ]);
+                    let ocp_map_slice = ocp_map.slice(s![
+                        k,
+                        (i - w_u)..(i + w_u + 1),
+                        (j - w_u)..(j + w_u + 1)
+                    ]);
+                    let curr_depth_filtered = &curr_depth_slice * &ocp_map_slice;
+                    let weighted_local_average =
+                        (curr_depth_filtered.sum() / (ocp_map_slice.sum())) + bias * 20.0;
+                    if ocp_map[[k, i, j]] == 1.0
+                        && curr_depth[[depth_idx, i, j]] * bias > weighted_local_average * bias
+                    {
+                        row_updates.push((k, i, j));
+                    }
+                    bias *= -1.0;
+                }
+            }
+            row_updates
+        };
+        let updates: Vec<(usize, usize, usize)> = if threads == 0 {
+            row_range.flat_map(decide_row).collect()
+        } else {
+            let pool = ThreadPoolBuilder::new()
+                .num_threads(threads as usize)
+                .build()
+                .unwrap();
+            pool.install(|| row_range.into_par_iter().flat_map(decide_row).collect())
+        };
+        for (k, i, j) in updates {
+            ocp_map[[k, i, j]] = 0.0;
+            img.slice_mut(s![k, i, j, ..]).fill(255);
+            freqs[k] += 1;
+        }
+        for i in 0..images {
+            println!("{} points removed from projection {}", &freqs[i], &i);
+        }
+    }
+    if morphology == 1 || morphology == 2 {
+        let mut opened: Vec<u64> = vec![0; images];
+        let mut closed: Vec<u64> = vec![0; images];
+        for k in 0..images {
+            let before = ocp_map.slice(s![k, .., ..]).to_owned();
+            let after = apply_morphology(&before, morphology, structure, iterations);
+            for i in 0..rows {
+                for j in 0..columns {
+                    if before[[i, j]] == 1.0 && after[[i, j]] == 0.0 {
+                        img.slice_mut(s![k, i, j, ..]).fill(255);
+                        opened[k] += 1;
+                    } else if before[[i, j]] == 0.0 && after[[i, j]] == 1.0 {
+                        if let Some(color) = nearest_occupied_color(
+                            &img,
+                            &before,
+                            k,
+                            i,
+                            j,
+                            iterations.max(1) as i64 + 1,
+                        ) {
+                            img.slice_mut(s![k, i, j, ..])
+                                .assign(&Array::from_vec(color));
+                        }
+                        closed[k] += 1;
+                    }
+                }
+            }
+            ocp_map.slice_mut(s![k, .., ..]).assign(&after);
+        }
+        for k in 0..images {
+            println!(
+                "{} points cleared by opening and {} holes filled by closing in projection {}",
+                &opened[k], &closed[k], &k
+            );
+        }
+    }
+    // Fill unoccupied pixels by directional prediction instead of leaving
+    // them white; marked ocp_map == 2 so inverse projection ignores them.
+    if intra_prediction > 0 {
+        let window = intra_window.max(1) as usize;
+        for k in 0..images {
+            for i in 0..rows {
+                for j in 0..columns {
+                    if ocp_map[[k, i, j]] != 0.0 {
+                        continue;
+                    }
+                    let predicted = match intra_prediction {
+                        1 => predict_dc(&img, &ocp_map, k, i, j, channels, window),
+                        2 => predict_horizontal(&img, &ocp_map, k, i, j, channels),
+                        _ => predict_vertical(&img, &ocp_map, k, i, j, channels),
+                    };
+                    img.slice_mut(s![k, i, j, ..])
+                        .assign(&Array::from_vec(predicted));
+                    ocp_map[[k, i, j]] = 2.0;
+                }
+            }
+        }
+    }
+    let mut offsets_arr = Array2::<f64>::zeros((num_directions, 3));
+    for d in 0..num_directions {
+        for c in 0..3 {
+            offsets_arr[[d, c]] = offsets[d][c];
+        }
+    }
+    return Ok((img, ocp_map, min_depth, max_depth, offsets_arr));
+}
+
+// Companion to `orthographic_projection`: re-emits the 3D point behind every
+// occupied pixel by undoing that direction's rotation and offset.
+// `view_directions`/`offsets` must be the exact values `orthographic_projection`
+// used to produce `images`/`ocp_map`/`depth_maps`. Duplicate voxels across
+// planes are deduplicated.
+#[pyfunction]
+fn inverse_orthographic_projection(
+    images: PyReadonlyArray4<u64>,
+    ocp_map: PyReadonlyArray3<f64>,
+    depth_maps: (PyReadonlyArray3<f64>, PyReadonlyArray3<f64>),
+    offsets: PyReadonlyArray2<f64>,
+    view_directions: Vec<Vec<Vec<f64>>>,
+    precision: u64,
+) -> PyResult<(Vec<Vec<f64>>, Vec<Vec<f64>>)> {
+    let (max_depth, min_depth) = depth_maps;
+    inverse_orthographic_projection_core(
+        images.as_array(),
+        ocp_map.as_array(),
+        max_depth.as_array(),
+        min_depth.as_array(),
+        offsets.as_array(),
+        &view_directions,
+        precision,
+    )
+    .map_err(PyValueError::new_err)
+}
+
+// Plain-Rust core behind `inverse_orthographic_projection`, free of PyO3
+// types so it can be exercised directly from tests.
+fn inverse_orthographic_projection_core(
+    images: ArrayView4<u64>,
+    ocp_map: ArrayView3<f64>,
+    max_depth: ArrayView3<f64>,
+    min_depth: ArrayView3<f64>,
+    offsets: ArrayView2<f64>,
+    view_directions: &Vec<Vec<Vec<f64>>>,
+    precision: u64,
+) -> Result<(Vec<Vec<f64>>, Vec<Vec<f64>>), String> {
+    let max_bound_f64 = (1u64 << precision) as f64;
+    let matrices = if view_directions.is_empty() {
+        default_view_matrices()
+    } else {
+        view_directions_to_matrices(view_directions)?
+    };
+    let (planes, rows, columns) = ocp_map.dim();
+    let mut seen: HashSet<(u64, u64, u64)> = HashSet::new();
+    let mut points: Vec<Vec<f64>> = Vec::new();
+    let mut colors: Vec<Vec<f64>> = Vec::new();
+    for k in 0usize..planes {
+        let j = k / 2;
+        let matrix = &matrices[j];
+        let offset = [offsets[[j, 0]], offsets[[j, 1]], offsets[[j, 2]]];
+        let depth_map = if k % 2 == 0 { &max_depth } else { &min_depth };
+        for k1 in 0..rows {
+            for k2 in 0..columns {
+                // Only ocp_map == 1 is a real sample; 0 is background, 2 is inpainting.
+                if ocp_map[[k, k1, k2]] != 1.0 {
+                    continue;
+                }
+                let shifted = [k1 as f64 + offset[0], k2 as f64 + offset[1], depth_map[[j, k1, k2]] + offset[2]];
+                let point = inverse_rotate_point(&shifted, matrix).to_vec();
+                if point[0] < 0.0
+                    || point[1] < 0.0
+                    || point[2] < 0.0
+                    || point[0] >= max_bound_f64
+                    || point[1] >= max_bound_f64
+                    || point[2] >= max_bound_f64
                 {
-                    ocp_map[[k, i, j]] = 0.0;
-                    img.slice_mut(s![k, i, j, ..]).fill(255);
-                    freqs[k] += 1
+                    continue;
                 }
-                bias *= -1.0;
+                let key = (
+                    point[0].floor() as u64,
+                    point[1].floor() as u64,
+                    point[2].floor() as u64,
+                );
+                if !seen.insert(key) {
+                    continue;
+                }
+                let color = images.slice(s![k, k1, k2, ..]).iter().map(|&c| c as f64).collect();
+                points.push(point);
+                colors.push(color);
             }
         }
     }
-    for i in 0..6 {
-        println!("{} points removed from projection {}", &freqs[i], &i);
+    return Ok((points, colors));
+}
+
+// Voxel grid keyed on floored point coordinates, for nearest-neighbor queries.
+fn build_voxel_grid(points: &Vec<Vec<f64>>) -> HashMap<(i64, i64, i64), Vec<usize>> {
+    let mut grid: HashMap<(i64, i64, i64), Vec<usize>> = HashMap::new();
+    for (idx, p) in points.iter().enumerate() {
+        let key = (p[0].floor() as i64, p[1].floor() as i64, p[2].floor() as i64);
+        grid.entry(key).or_insert_with(Vec::new).push(idx);
     }
-    return (img.to_pyarray(py), ocp_map.to_pyarray(py));
+    return grid;
+}
+
+// Nearest distance from `point` to `candidates`, searching voxel shells of
+// growing Chebyshev radius and stopping once no farther shell can beat the best.
+fn nearest_distance(
+    point: &Vec<f64>,
+    candidates: &Vec<Vec<f64>>,
+    grid: &HashMap<(i64, i64, i64), Vec<usize>>,
+) -> f64 {
+    if candidates.is_empty() {
+        return f64::INFINITY;
+    }
+    let key = (
+        point[0].floor() as i64,
+        point[1].floor() as i64,
+        point[2].floor() as i64,
+    );
+    let mut best = f64::INFINITY;
+    let mut radius: i64 = 0;
+    loop {
+        for dx in -radius..=radius {
+            for dy in -radius..=radius {
+                for dz in -radius..=radius {
+                    if dx.abs() != radius && dy.abs() != radius && dz.abs() != radius {
+                        continue;
+                    }
+                    let cell = (key.0 + dx, key.1 + dy, key.2 + dz);
+                    if let Some(indices) = grid.get(&cell) {
+                        for &idx in indices {
+                            let q = &candidates[idx];
+                            let d = ((point[0] - q[0]).powi(2)
+                                + (point[1] - q[1]).powi(2)
+                                + (point[2] - q[2]).powi(2))
+                            .sqrt();
+                            if d < best {
+                                best = d;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        // A voxel at Chebyshev radius `r` can hold a point no closer than `r - 1`.
+        if best.is_finite() && (radius as f64 - 1.0) > best {
+            break;
+        }
+        radius += 1;
+    }
+    return best;
+}
+
+// Max, mean and RMS nearest-neighbor distance from `from` to `to`.
+fn directed_hausdorff_stats(from: &Vec<Vec<f64>>, to: &Vec<Vec<f64>>) -> (f64, f64, f64) {
+    if from.is_empty() || to.is_empty() {
+        return (f64::INFINITY, f64::INFINITY, f64::INFINITY);
+    }
+    let grid = build_voxel_grid(to);
+    let mut max_d: f64 = 0.0;
+    let mut sum = 0.0;
+    let mut sum_sq = 0.0;
+    for p in from {
+        let d = nearest_distance(p, to, &grid);
+        if d > max_d {
+            max_d = d;
+        }
+        sum += d;
+        sum_sq += d * d;
+    }
+    let n = from.len() as f64;
+    return (max_d, sum / n, (sum_sq / n).sqrt());
+}
+
+// Symmetric Hausdorff distance between two point clouds (voxel-accelerated).
+// Returns (symmetric, a_to_b_max, b_to_a_max, mean, rms).
+#[pyfunction]
+fn hausdorff_distance(
+    points_a: Vec<Vec<f64>>,
+    points_b: Vec<Vec<f64>>,
+) -> (f64, f64, f64, f64, f64) {
+    let (a_to_b_max, a_to_b_mean, a_to_b_rms) = directed_hausdorff_stats(&points_a, &points_b);
+    let (b_to_a_max, b_to_a_mean, b_to_a_rms) = directed_hausdorff_stats(&points_b, &points_a);
+    let symmetric = a_to_b_max.max(b_to_a_max);
+    let mean = (a_to_b_mean + b_to_a_mean) / 2.0;
+    let rms = ((a_to_b_rms.powi(2) + b_to_a_rms.powi(2)) / 2.0).sqrt();
+    return (symmetric, a_to_b_max, b_to_a_max, mean, rms);
 }
 
 #[pymodule]
 fn projectors(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(orthographic_projection, m)?)?;
+    m.add_function(wrap_pyfunction!(inverse_orthographic_projection, m)?)?;
+    m.add_function(wrap_pyfunction!(hausdorff_distance, m)?)?;
     Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A handful of points placed so every default view direction maps each
+    // one to a distinct pixel, so no point hides another and the six planes'
+    // copies of each point deduplicate back to one.
+    fn sample_cloud() -> (Vec<Vec<f64>>, Vec<Vec<f64>>) {
+        let points = vec![
+            vec![1.0, 2.0, 3.0],
+            vec![7.0, 1.0, 1.0],
+            vec![2.0, 9.0, 4.0],
+            vec![5.0, 5.0, 5.0],
+        ];
+        let colors = vec![
+            vec![10.0, 20.0, 30.0],
+            vec![40.0, 50.0, 60.0],
+            vec![70.0, 80.0, 90.0],
+            vec![100.0, 110.0, 120.0],
+        ];
+        (points, colors)
+    }
+
+    #[test]
+    fn round_trip_recovers_points_and_colors() {
+        let (points, colors) = sample_cloud();
+        let precision = 4;
+        let (img, ocp_map, min_depth, max_depth, offsets) = orthographic_projection_core(
+            points.clone(),
+            colors.clone(),
+            precision,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            Vec::new(),
+            0,
+        )
+        .expect("forward projection should succeed");
+        let (out_points, out_colors) = inverse_orthographic_projection_core(
+            img.view(),
+            ocp_map.view(),
+            max_depth.view(),
+            min_depth.view(),
+            offsets.view(),
+            &Vec::new(),
+            precision,
+        )
+        .expect("inverse projection should succeed");
+        assert_eq!(out_points.len(), points.len());
+        for (i, p) in points.iter().enumerate() {
+            let idx = out_points
+                .iter()
+                .position(|q| {
+                    (q[0] - p[0]).abs() < 1e-9 && (q[1] - p[1]).abs() < 1e-9 && (q[2] - p[2]).abs() < 1e-9
+                })
+                .expect("every input point should round-trip");
+            assert_eq!(out_colors[idx], colors[i]);
+        }
+    }
+
+    #[test]
+    fn rotate_then_inverse_rotate_is_identity() {
+        let point = [1.5, -2.25, 3.75];
+        for matrix in default_view_matrices() {
+            let rotated = rotate_point(&point, &matrix);
+            let back = inverse_rotate_point(&rotated, &matrix);
+            for c in 0..3 {
+                assert!((back[c] - point[c]).abs() < 1e-9);
+            }
+        }
+        let euler = euler_to_matrix(0.4, -0.7, 1.1);
+        let rotated = rotate_point(&point, &euler);
+        let back = inverse_rotate_point(&rotated, &euler);
+        for c in 0..3 {
+            assert!((back[c] - point[c]).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn euler_to_matrix_is_orthonormal() {
+        let matrix = euler_to_matrix(0.3, 0.9, -1.4);
+        // R^T * R should be the identity for any proper rotation matrix.
+        let product = matmul3(
+            &[
+                [matrix[0][0], matrix[1][0], matrix[2][0]],
+                [matrix[0][1], matrix[1][1], matrix[2][1]],
+                [matrix[0][2], matrix[1][2], matrix[2][2]],
+            ],
+            &matrix,
+        );
+        for r in 0..3 {
+            for c in 0..3 {
+                let expected = if r == c { 1.0 } else { 0.0 };
+                assert!((product[r][c] - expected).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn round_trip_through_an_oblique_view_direction() {
+        let (points, colors) = sample_cloud();
+        let precision = 6;
+        let view_directions = vec![vec![vec![0.3, 0.2, 0.1]]];
+        let (img, ocp_map, min_depth, max_depth, offsets) = orthographic_projection_core(
+            points.clone(),
+            colors.clone(),
+            precision,
+            0,
+            0,
+            0,
+            0,
+            0,
+            0,
+            view_directions.clone(),
+            0,
+        )
+        .expect("forward projection with an oblique direction should succeed");
+        let (out_points, out_colors) = inverse_orthographic_projection_core(
+            img.view(),
+            ocp_map.view(),
+            max_depth.view(),
+            min_depth.view(),
+            offsets.view(),
+            &view_directions,
+            precision,
+        )
+        .expect("inverse projection with an oblique direction should succeed");
+        let matrix = euler_to_matrix(0.3, 0.2, 0.1);
+        assert_eq!(out_points.len(), points.len());
+        for (i, p) in points.iter().enumerate() {
+            // In-plane coordinates come back snapped to the pixel grid; depth is exact.
+            let r = rotate_point(p, &matrix);
+            let snapped = [
+                (r[0] - offsets[[0, 0]]).floor() + offsets[[0, 0]],
+                (r[1] - offsets[[0, 1]]).floor() + offsets[[0, 1]],
+                r[2],
+            ];
+            let e = inverse_rotate_point(&snapped, &matrix);
+            let idx = out_points
+                .iter()
+                .position(|q| {
+                    (q[0] - e[0]).abs() < 1e-6 && (q[1] - e[1]).abs() < 1e-6 && (q[2] - e[2]).abs() < 1e-6
+                })
+                .expect("every input point should come back, snapped to the oblique view's pixel grid");
+            assert_eq!(out_colors[idx], colors[i]);
+        }
+    }
+
+    #[test]
+    fn malformed_view_direction_is_rejected_not_panicked() {
+        let result = view_directions_to_matrices(&vec![vec![vec![1.0, 0.0]]]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn hausdorff_distance_of_identical_clouds_is_zero() {
+        let (points, _) = sample_cloud();
+        let (symmetric, a_to_b_max, b_to_a_max, mean, rms) =
+            hausdorff_distance(points.clone(), points);
+        assert_eq!(symmetric, 0.0);
+        assert_eq!(a_to_b_max, 0.0);
+        assert_eq!(b_to_a_max, 0.0);
+        assert_eq!(mean, 0.0);
+        assert_eq!(rms, 0.0);
+    }
+
+    #[test]
+    fn hausdorff_distance_of_a_uniform_translation_matches_the_shift() {
+        let (points, _) = sample_cloud();
+        let shift = 3.0;
+        let shifted: Vec<Vec<f64>> = points
+            .iter()
+            .map(|p| vec![p[0] + shift, p[1], p[2]])
+            .collect();
+        let (symmetric, a_to_b_max, b_to_a_max, mean, rms) =
+            hausdorff_distance(points, shifted);
+        assert!((symmetric - shift).abs() < 1e-9);
+        assert!((a_to_b_max - shift).abs() < 1e-9);
+        assert!((b_to_a_max - shift).abs() < 1e-9);
+        assert!((mean - shift).abs() < 1e-9);
+        assert!((rms - shift).abs() < 1e-9);
+    }
+
+    #[test]
+    fn filtering_agrees_between_sequential_and_parallel_threads() {
+        // A flat 8x8 plate with one point lifted far above it, so filtering
+        // actually removes that pixel and the two thread counts have
+        // something to disagree on.
+        let precision = 5;
+        let mut points = Vec::new();
+        let mut colors = Vec::new();
+        for i in 0..8u64 {
+            for j in 0..8u64 {
+                let z = if i == 4 && j == 4 { 30.0 } else { 0.0 };
+                points.push(vec![i as f64, j as f64, z]);
+                colors.push(vec![1.0, 2.0, 3.0]);
+            }
+        }
+        let sequential = orthographic_projection_core(
+            points.clone(),
+            colors.clone(),
+            precision,
+            2,
+            0,
+            0,
+            0,
+            0,
+            0,
+            Vec::new(),
+            0,
+        )
+        .expect("sequential filtering should succeed");
+        let parallel = orthographic_projection_core(
+            points,
+            colors,
+            precision,
+            2,
+            0,
+            0,
+            0,
+            0,
+            0,
+            Vec::new(),
+            2,
+        )
+        .expect("parallel filtering should succeed");
+        assert_eq!(sequential.1[[4, 4, 4]], 0.0);
+        assert_eq!(sequential.0, parallel.0);
+        assert_eq!(sequential.1, parallel.1);
+        assert_eq!(sequential.2, parallel.2);
+        assert_eq!(sequential.3, parallel.3);
+    }
 }
\ No newline at end of file